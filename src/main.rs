@@ -1,20 +1,94 @@
+use std::collections::HashSet;
 use std::fmt::Display;
-use std::io;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
 use thiserror::Error;
+use walkdir::WalkDir;
 
 /// Image conversion utility
 #[derive(Parser, Debug)]
 #[clap(author = "Louis-Philippe Turmel", version, about, long_about = None)]
 pub struct Cli {
-    /// The input file to use
+    /// The input file or, in batch mode, a directory to scan
     #[clap(long, short)]
     input: String,
-    /// The output file to use
+    /// The output file to use (single-file mode only)
     #[clap(long, short)]
-    output: String,
+    output: Option<String>,
+    /// Target extension for batch mode, e.g. "png"
+    #[clap(long)]
+    to: Option<String>,
+    /// Directory to write converted files into (batch mode); defaults to next to the originals
+    #[clap(long)]
+    output_dir: Option<String>,
+    /// Recurse into subdirectories when input is a directory
+    #[clap(long)]
+    recursive: bool,
+    /// Restrict batch mode to a named preset of extensions
+    #[clap(long)]
+    filter: Option<Filter>,
+    /// Extensions to skip in batch mode, e.g. "gif,bmp"
+    #[clap(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+    /// Fail instead of warning when the input's content doesn't match its extension
+    #[clap(long)]
+    strict: bool,
+    /// Encode quality for JPEG and lossy WebP output (1-100)
+    #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+    /// Use lossless WebP encoding instead of lossy
+    #[clap(long)]
+    lossless: bool,
+    /// Resize to an exact WIDTHxHEIGHT before encoding, cropping to fill
+    #[clap(long)]
+    resize: Option<String>,
+    /// Downscale so neither dimension exceeds N, preserving aspect ratio
+    #[clap(long)]
+    max_dim: Option<u32>,
+    /// Filter used when resizing
+    #[clap(long, default_value = "lanczos3")]
+    resize_filter: ResizeFilter,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Filter {
+    Images,
+}
+
+impl Filter {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Filter::Images => &["png", "jpg", "jpeg", "webp", "gif", "bmp", "tiff"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -25,6 +99,16 @@ enum Error {
     Extension(String),
     #[error("IO error: {0}")]
     Io(io::Error),
+    #[error("The file content looks like {actual} but was given as {expected}")]
+    FormatMismatch { expected: Extension, actual: Extension },
+    #[error("Usage error: {0}")]
+    Usage(String),
+    #[error("Encoding error: {0}")]
+    Encode(String),
+    #[error("Output collision: {0}")]
+    Collision(String),
+    #[error("{0} of {1} file(s) failed to convert")]
+    BatchFailed(usize, usize),
 }
 impl From<image::ImageError> for Error {
     fn from(err: image::ImageError) -> Self {
@@ -44,20 +128,21 @@ impl From<String> for Error {
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum Extension {
-    Png,
-    Jpeg,
-    Webp,
-}
+/// A file extension, backed by `image::ImageFormat` so this crate supports every format the
+/// `image` crate can decode/encode rather than a hand-picked subset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Extension(ImageFormat);
 
 impl Display for Extension {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Extension::Png => write!(f, "png"),
-            Extension::Jpeg => write!(f, "jpeg"),
-            Extension::Webp => write!(f, "webp"),
-        }
+        let name = match self.0 {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Gif => "gif",
+            other => other.extensions_str().first().copied().unwrap_or("unknown"),
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -66,19 +151,33 @@ impl FromStr for Extension {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts = s.split('.').collect::<Vec<_>>();
-        let s = parts
+        let ext = parts
             .last()
             .cloned()
             .ok_or_else(|| format!("The file {} has no extension, please specify one", s))?;
-        match s {
-            "png" => Ok(Extension::Png),
-            "jpg" => Ok(Extension::Jpeg),
-            "jpeg" => Ok(Extension::Jpeg),
-            "webp" => Ok(Extension::Webp),
-            _ => Err(format!("The extension {} is not supported", s).into()),
-        }
+        ImageFormat::from_extension(ext)
+            .map(Extension)
+            .ok_or_else(|| format!("The extension {} is not supported", ext).into())
     }
 }
+
+/// Sniffs the first bytes of a file and returns the `Extension` implied by its magic number,
+/// or `None` if the content doesn't match one of the magic numbers checked here. Only JPEG, PNG,
+/// WebP and GIF are recognized; content in any other format yields `None`.
+fn detect_format(bytes: &[u8]) -> Option<Extension> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(Extension(ImageFormat::Jpeg))
+    } else if bytes.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some(Extension(ImageFormat::Png))
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(Extension(ImageFormat::WebP))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(Extension(ImageFormat::Gif))
+    } else {
+        None
+    }
+}
+
 fn main() {
     if let Err(err) = run() {
         eprintln!("\x1b[31m{}\x1b[0m", err);
@@ -88,12 +187,25 @@ fn main() {
 fn run() -> Result<(), Error> {
     let cli = Cli::parse();
 
+    if Path::new(&cli.input).is_dir() {
+        return run_batch(&cli);
+    }
+
+    let output = cli
+        .output
+        .clone()
+        .ok_or_else(|| Error::Usage("--output is required when --input is a file".to_string()))?;
+
     let input_ext = cli.input.parse::<Extension>()?;
-    let output_ext = cli.output.parse::<Extension>()?;
+    let output_ext = output.parse::<Extension>()?;
+
+    let bytes = std::fs::read(&cli.input)?;
+    warn_or_reject_mismatch(&cli.input, &bytes, input_ext, cli.strict)?;
 
-    let img = image::open(cli.input)?;
+    let img = image::load_from_memory(&bytes)?;
+    let img = apply_resize(img, &cli)?;
 
-    img.save(cli.output)?;
+    encode_image(&img, Path::new(&output), output_ext, &cli)?;
 
     println!(
         "Image successfully converted from {} to {}",
@@ -102,30 +214,246 @@ fn run() -> Result<(), Error> {
     Ok(())
 }
 
+/// Walks `cli.input` for files matching the active filter/exclude list and converts each one to
+/// `cli.to`, collecting per-file successes and failures instead of bailing on the first error.
+fn run_batch(cli: &Cli) -> Result<(), Error> {
+    let to_ext = cli
+        .to
+        .as_deref()
+        .ok_or_else(|| Error::Usage("--to <extension> is required when --input is a directory".to_string()))?
+        .parse::<Extension>()?;
+
+    let allowed = cli.filter.unwrap_or(Filter::Images).extensions();
+    let excluded: Vec<String> = cli.exclude.iter().map(|e| e.to_lowercase()).collect();
+
+    let walker = WalkDir::new(&cli.input).max_depth(if cli.recursive { usize::MAX } else { 1 });
+
+    let mut successes = 0usize;
+    let mut failures: Vec<(PathBuf, Error)> = Vec::new();
+    let mut seen_dests: HashSet<PathBuf> = HashSet::new();
+
+    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let ext = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.to_lowercase(),
+            None => continue,
+        };
+        if !allowed.contains(&ext.as_str()) || excluded.contains(&ext) {
+            continue;
+        }
+
+        match convert_one(path, to_ext, cli, &mut seen_dests) {
+            Ok(dest) => {
+                successes += 1;
+                println!("{} -> {}", path.display(), dest.display());
+            }
+            Err(err) => failures.push((path.to_path_buf(), err)),
+        }
+    }
+
+    for (path, err) in &failures {
+        eprintln!("\x1b[31m{}: {}\x1b[0m", path.display(), err);
+    }
+    println!("{} converted, {} failed", successes, failures.len());
+
+    if !failures.is_empty() {
+        return Err(Error::BatchFailed(failures.len(), successes + failures.len()));
+    }
+
+    Ok(())
+}
+
+/// Converts a single file discovered during batch mode, writing the result into `--output-dir`
+/// (mirroring the file's subdirectory under the input directory) or next to the original file
+/// when unset. `seen_dests` tracks every destination already written by this batch run so files
+/// that map to the same destination (e.g. `photo.png` and `photo.gif` in one directory) are
+/// reported as a collision instead of silently overwriting each other.
+fn convert_one(
+    path: &Path,
+    to_ext: Extension,
+    cli: &Cli,
+    seen_dests: &mut HashSet<PathBuf>,
+) -> Result<PathBuf, Error> {
+    let dest_dir = match &cli.output_dir {
+        Some(dir) => {
+            let rel_dir = path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&cli.input).ok())
+                .filter(|rel| !rel.as_os_str().is_empty());
+            match rel_dir {
+                Some(rel) => PathBuf::from(dir).join(rel),
+                None => PathBuf::from(dir),
+            }
+        }
+        None => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    std::fs::create_dir_all(&dest_dir)?;
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let dest = dest_dir.join(format!("{}.{}", stem, to_ext));
+
+    if seen_dests.contains(&dest) {
+        return Err(Error::Collision(format!(
+            "{} would overwrite {}, already written by this batch run",
+            path.display(),
+            dest.display()
+        )));
+    }
+
+    let input_ext = path.to_string_lossy().parse::<Extension>()?;
+    let bytes = std::fs::read(path)?;
+    warn_or_reject_mismatch(&path.to_string_lossy(), &bytes, input_ext, cli.strict)?;
+
+    let img = image::load_from_memory(&bytes)?;
+    let img = apply_resize(img, cli)?;
+
+    encode_image(&img, &dest, to_ext, cli)?;
+    seen_dests.insert(dest.clone());
+
+    Ok(dest)
+}
+
+/// Compares the content-sniffed format against the extension-derived one, warning (or returning
+/// an error in `--strict` mode) when they disagree.
+fn warn_or_reject_mismatch(
+    label: &str,
+    bytes: &[u8],
+    expected: Extension,
+    strict: bool,
+) -> Result<(), Error> {
+    if let Some(actual) = detect_format(bytes) {
+        if actual != expected {
+            if strict {
+                return Err(Error::FormatMismatch { expected, actual });
+            }
+            eprintln!(
+                "\x1b[33mwarning: {} looks like {} but was given as {}\x1b[0m",
+                label, actual, expected
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Applies `--resize` (exact dimensions, cropping to fill) or `--max-dim` (bounding box,
+/// preserving aspect ratio) to `img`, in that order of precedence. Returns `img` unchanged
+/// when neither flag is set.
+fn apply_resize(img: DynamicImage, cli: &Cli) -> Result<DynamicImage, Error> {
+    let filter = cli.resize_filter.into();
+    if let Some(spec) = &cli.resize {
+        let (width, height) = parse_dimensions(spec)?;
+        return Ok(img.resize_to_fill(width, height, filter));
+    }
+    if let Some(max_dim) = cli.max_dim {
+        if img.width() > max_dim || img.height() > max_dim {
+            return Ok(img.resize(max_dim, max_dim, filter));
+        }
+        return Ok(img);
+    }
+    Ok(img)
+}
+
+/// Parses a `WIDTHxHEIGHT` spec as passed to `--resize`.
+fn parse_dimensions(spec: &str) -> Result<(u32, u32), Error> {
+    let (width, height) = spec
+        .split_once('x')
+        .ok_or_else(|| format!("Invalid --resize value '{}', expected WIDTHxHEIGHT", spec))?;
+    let width = width
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid width in --resize value '{}'", spec))?;
+    let height = height
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid height in --resize value '{}'", spec))?;
+    Ok((width, height))
+}
+
+/// Encodes `img` to `dest` as `ext`, using an explicit encoder for formats with tunable
+/// quality (JPEG, WebP) instead of relying on `DynamicImage::save`'s defaults.
+fn encode_image(img: &DynamicImage, dest: &Path, ext: Extension, cli: &Cli) -> Result<(), Error> {
+    match ext.0 {
+        ImageFormat::Jpeg => {
+            let quality = cli.quality.unwrap_or(80);
+            let writer = BufWriter::new(File::create(dest)?);
+            JpegEncoder::new_with_quality(writer, quality).encode_image(img)?;
+        }
+        ImageFormat::WebP => {
+            let encoder = webp::Encoder::from_image(img).map_err(|e| Error::Encode(e.to_string()))?;
+            let data = if cli.lossless {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(cli.quality.unwrap_or(80) as f32)
+            };
+            std::fs::write(dest, &*data)?;
+        }
+        other => img.save_with_format(dest, other)?,
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Extension;
+    use super::{convert_one, detect_format, parse_dimensions, Cli, Error, Extension, Filter, ResizeFilter};
+    use image::ImageFormat;
+    use std::collections::HashSet;
     use std::str::FromStr;
 
     #[test]
     fn test_extension_enum_variants() {
-        assert_eq!(format!("{}", Extension::Png), "png");
-        assert_eq!(format!("{}", Extension::Jpeg), "jpeg");
-        assert_eq!(format!("{}", Extension::Webp), "webp");
+        assert_eq!(format!("{}", Extension(ImageFormat::Png)), "png");
+        assert_eq!(format!("{}", Extension(ImageFormat::Jpeg)), "jpeg");
+        assert_eq!(format!("{}", Extension(ImageFormat::WebP)), "webp");
     }
 
     #[test]
     fn test_from_str_valid_extensions() {
-        assert_eq!(Extension::from_str("file.png").unwrap(), Extension::Png);
-        assert_eq!(Extension::from_str("file.jpg").unwrap(), Extension::Jpeg);
-        assert_eq!(Extension::from_str("file.jpeg").unwrap(), Extension::Jpeg);
-        assert_eq!(Extension::from_str("file.webp").unwrap(), Extension::Webp);
+        assert_eq!(
+            Extension::from_str("file.png").unwrap(),
+            Extension(ImageFormat::Png)
+        );
+        assert_eq!(
+            Extension::from_str("file.jpg").unwrap(),
+            Extension(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            Extension::from_str("file.jpeg").unwrap(),
+            Extension(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            Extension::from_str("file.webp").unwrap(),
+            Extension(ImageFormat::WebP)
+        );
+    }
+
+    #[test]
+    fn test_from_str_newly_supported_extensions() {
+        assert_eq!(
+            Extension::from_str("file.bmp").unwrap(),
+            Extension(ImageFormat::Bmp)
+        );
+        assert_eq!(
+            Extension::from_str("file.gif").unwrap(),
+            Extension(ImageFormat::Gif)
+        );
+        assert_eq!(
+            Extension::from_str("file.tiff").unwrap(),
+            Extension(ImageFormat::Tiff)
+        );
+        assert_eq!(
+            Extension::from_str("file.ico").unwrap(),
+            Extension(ImageFormat::Ico)
+        );
+        assert_eq!(
+            Extension::from_str("file.avif").unwrap(),
+            Extension(ImageFormat::Avif)
+        );
     }
 
     #[test]
     fn test_from_str_invalid_extension() {
-        assert!(Extension::from_str("file.bmp").is_err());
-        assert!(Extension::from_str("file.gif").is_err());
+        assert!(Extension::from_str("file.docx").is_err());
     }
 
     #[test]
@@ -142,7 +470,110 @@ mod tests {
     fn test_from_str_multiple_dots() {
         assert_eq!(
             Extension::from_str("file.some.jpg").unwrap(),
-            Extension::Jpeg
+            Extension(ImageFormat::Jpeg)
         );
     }
+
+    #[test]
+    fn test_detect_format_jpeg() {
+        assert_eq!(
+            detect_format(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(Extension(ImageFormat::Jpeg))
+        );
+    }
+
+    #[test]
+    fn test_detect_format_png() {
+        let bytes = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00];
+        assert_eq!(detect_format(&bytes), Some(Extension(ImageFormat::Png)));
+    }
+
+    #[test]
+    fn test_detect_format_webp() {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(b"WEBP");
+        assert_eq!(detect_format(&bytes), Some(Extension(ImageFormat::WebP)));
+    }
+
+    #[test]
+    fn test_detect_format_gif() {
+        assert_eq!(
+            detect_format(b"GIF89a"),
+            Some(Extension(ImageFormat::Gif))
+        );
+    }
+
+    #[test]
+    fn test_detect_format_unknown() {
+        assert_eq!(detect_format(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_filter_images_extensions() {
+        let extensions = Filter::Images.extensions();
+        assert!(extensions.contains(&"png"));
+        assert!(extensions.contains(&"gif"));
+        assert!(!extensions.contains(&"txt"));
+    }
+
+    #[test]
+    fn test_parse_dimensions_valid() {
+        assert_eq!(parse_dimensions("800x600").unwrap(), (800, 600));
+    }
+
+    #[test]
+    fn test_parse_dimensions_invalid() {
+        assert!(parse_dimensions("800").is_err());
+        assert!(parse_dimensions("800xabc").is_err());
+    }
+
+    fn test_cli(input: &str, output_dir: &str) -> Cli {
+        Cli {
+            input: input.to_string(),
+            output: None,
+            to: Some("jpeg".to_string()),
+            output_dir: Some(output_dir.to_string()),
+            recursive: true,
+            filter: None,
+            exclude: Vec::new(),
+            strict: false,
+            quality: None,
+            lossless: false,
+            resize: None,
+            max_dim: None,
+            resize_filter: ResizeFilter::Lanczos3,
+        }
+    }
+
+    #[test]
+    fn test_convert_one_mirrors_subdirectories_and_rejects_collisions() {
+        let base = std::env::temp_dir().join(format!("imgy_test_{}", std::process::id()));
+        let input_dir = base.join("input");
+        let dir_a = input_dir.join("a");
+        let dir_b = input_dir.join("b");
+        std::fs::create_dir_all(&dir_a).unwrap();
+        std::fs::create_dir_all(&dir_b).unwrap();
+
+        let img = image::RgbImage::new(2, 2);
+        img.save(dir_a.join("photo.png")).unwrap();
+        img.save(dir_b.join("photo.png")).unwrap();
+
+        let output_dir = base.join("out");
+        let cli = test_cli(&input_dir.to_string_lossy(), &output_dir.to_string_lossy());
+        let to_ext = Extension::from_str("jpeg").unwrap();
+        let mut seen_dests = HashSet::new();
+
+        let dest_a = convert_one(&dir_a.join("photo.png"), to_ext, &cli, &mut seen_dests).unwrap();
+        assert_eq!(dest_a, output_dir.join("a").join("photo.jpeg"));
+        assert!(dest_a.exists());
+
+        let dest_b = convert_one(&dir_b.join("photo.png"), to_ext, &cli, &mut seen_dests).unwrap();
+        assert_eq!(dest_b, output_dir.join("b").join("photo.jpeg"));
+
+        let err = convert_one(&dir_a.join("photo.png"), to_ext, &cli, &mut seen_dests).unwrap_err();
+        assert!(matches!(err, Error::Collision(_)));
+
+        std::fs::remove_dir_all(&base).ok();
+    }
 }